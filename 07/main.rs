@@ -9,33 +9,26 @@ fn part1(rules: &[(String, Vec<(usize, String)>)]) -> i64 {
             graph.add_edge(gp, gnp, 1);
         }
     }
-    let mut with_path = 0;
-    for (node, _) in rules {
-        if node != "shiny gold"
-            && aoc::algo::has_path_connecting(&graph, node, &"shiny gold".to_string(), None)
-        {
-            with_path += 1;
-        }
-    }
-    with_path
+    let shiny_gold = "shiny gold".to_string();
+    aoc::algo::ancestors(&graph, &shiny_gold).len() as i64
 }
 
-fn sum_bags(bag: &str, rules: &[(String, Vec<(usize, String)>)]) -> usize {
+fn part2(rules: &[(String, Vec<(usize, String)>)]) -> i64 {
+    let mut graph = aoc::GraphMap::<&String, u64, aoc::Directed>::new();
     for (node, neighbors) in rules {
-        if node == bag {
-            let mut tot = 1;
-            for (c, n) in neighbors {
-                tot += c * sum_bags(n, rules);
-            }
-            return tot;
+        let gp = graph.add_node(node);
+        for (c, n) in neighbors {
+            let gnp = graph.add_node(n);
+            graph.add_edge(gp, gnp, *c as u64);
         }
     }
-    0
-}
-
-fn part2(rules: &[(String, Vec<(usize, String)>)]) -> i64 {
-    // - 1 as we're not counting the "shiny gold" bag
-    sum_bags("shiny gold", rules) as i64 - 1
+    let shiny_gold = "shiny gold".to_string();
+    // Bail out clearly on cyclic rules; the count below would otherwise be 0.
+    if let Err(cycle) = aoc::algo::topological_sort(&graph) {
+        panic!("bag containment rules contain a cycle: {:?}", cycle.nodes);
+    }
+    // weighted_descendant_count already excludes the "shiny gold" bag itself
+    aoc::algo::weighted_descendant_count(&graph, &shiny_gold) as i64
 }
 
 fn parse(lines: &[String]) -> Vec<(String, Vec<(usize, String)>)> {